@@ -0,0 +1,72 @@
+use fst::Automaton;
+use query::automaton_builder::AutomatonBuilder;
+use query::{AutomatonWeight, Query, Weight};
+use schema::Field;
+use std::fmt;
+use std::sync::Arc;
+use Result;
+use Searcher;
+
+/// A `Query` that matches every term accepted by an arbitrary
+/// `fst::Automaton` — a Levenshtein `DFA`, a regular expression, a
+/// hand-rolled prefix or range automaton, anything implementing the
+/// trait.
+///
+/// `FuzzyTermQuery` and `RegexQuery` are built on top of the same
+/// `AutomatonWeight` machinery this query uses; reach for
+/// `AutomatonQuery` directly when neither of those fits and you want
+/// to stream a custom automaton against a field's term dictionary.
+pub struct AutomatonQuery<A>
+where
+    A: Automaton,
+{
+    field: Field,
+    builder: Arc<AutomatonBuilder<A>>,
+}
+
+impl<A> AutomatonQuery<A>
+where
+    A: Automaton,
+{
+    /// Creates a new `AutomatonQuery` that runs `builder`'s automaton
+    /// against `field`'s term dictionary.
+    pub fn new(field: Field, builder: Arc<AutomatonBuilder<A>>) -> AutomatonQuery<A> {
+        AutomatonQuery { field, builder }
+    }
+}
+
+impl<A> fmt::Debug for AutomatonQuery<A>
+where
+    A: Automaton,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AutomatonQuery(field={:?})", self.field)
+    }
+}
+
+// Written by hand rather than `#[derive(Clone)]`: the derive would add
+// a spurious `A: Clone` bound on the impl, even though cloning an
+// `Arc<AutomatonBuilder<A>>` never requires `A: Clone`.
+impl<A> Clone for AutomatonQuery<A>
+where
+    A: Automaton,
+{
+    fn clone(&self) -> AutomatonQuery<A> {
+        AutomatonQuery {
+            field: self.field,
+            builder: Arc::clone(&self.builder),
+        }
+    }
+}
+
+impl<A> Query for AutomatonQuery<A>
+where
+    A: Automaton + 'static,
+{
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(Box::new(AutomatonWeight::new(
+            self.field,
+            Arc::clone(&self.builder),
+        )))
+    }
+}
@@ -0,0 +1,268 @@
+use common::BitSet;
+use core::SegmentReader;
+use levenshtein_automata::{LevenshteinAutomatonBuilder, DFA};
+use query::automaton_builder::AutomatonBuilder;
+use query::{AutomatonWeight, BitSetDocSet, ConstScorer};
+use query::{Query, Scorer, Weight};
+use schema::{Field, IndexRecordOption};
+use std::collections::HashMap;
+use std::sync::Arc;
+use DocId;
+use DocSet;
+use Result;
+use Searcher;
+
+/// Expands a single query term into several alternatives — known
+/// synonyms, the term split at a word boundary into two sub-words that
+/// both exist in the dictionary, and the term concatenated with the
+/// next query token — then matches documents containing any of them.
+///
+/// This mirrors MeiliSearch's "query enhancer": a query for "sea
+/// horse" should also match documents containing "seahorse", and a
+/// query for "teh" should also match its synonym "the", without the
+/// caller having to pre-expand the query themselves.
+#[derive(Debug, Clone)]
+pub struct ExpandedTermQuery {
+    field: Field,
+    term_text: String,
+    /// The token immediately following this one in the query, if any,
+    /// used to try a concatenation candidate (e.g. "sea" + "horse").
+    next_term_text: Option<String>,
+    /// Known synonyms for `term_text`.
+    synonyms: Vec<String>,
+    /// Edit distance allowed on each whole-word alternative (the term,
+    /// its synonyms and the concatenation). Split fragments are always
+    /// looked up exactly.
+    distance: u8,
+    transposition_cost_one: bool,
+}
+
+impl ExpandedTermQuery {
+    pub fn new(field: Field, term_text: String) -> ExpandedTermQuery {
+        ExpandedTermQuery {
+            field,
+            term_text,
+            next_term_text: None,
+            synonyms: Vec::new(),
+            distance: 0,
+            transposition_cost_one: true,
+        }
+    }
+
+    /// Also try concatenating `term_text` with `next_term_text`.
+    pub fn with_next_term(mut self, next_term_text: String) -> ExpandedTermQuery {
+        self.next_term_text = Some(next_term_text);
+        self
+    }
+
+    /// Looks `term_text` up in `synonym_map` and, if found, also tries
+    /// each of its synonyms.
+    pub fn with_synonyms(
+        mut self,
+        synonym_map: &HashMap<String, Vec<String>>,
+    ) -> ExpandedTermQuery {
+        if let Some(synonyms) = synonym_map.get(&self.term_text) {
+            self.synonyms = synonyms.clone();
+        }
+        self
+    }
+
+    /// Allows each whole-word alternative to match fuzzily rather than
+    /// exactly. Split fragments are still matched exactly.
+    pub fn with_distance(
+        mut self,
+        distance: u8,
+        transposition_cost_one: bool,
+    ) -> ExpandedTermQuery {
+        self.distance = distance;
+        self.transposition_cost_one = transposition_cost_one;
+        self
+    }
+
+    /// The whole-word alternatives worth matching: the term itself,
+    /// its synonyms, and (if a next term is known) the concatenation
+    /// of the two. Split candidates are handled separately, since they
+    /// need to be matched as adjacent fragment pairs, not whole words.
+    fn word_alternatives(&self) -> Vec<String> {
+        let mut alternatives = vec![self.term_text.clone()];
+        alternatives.extend(self.synonyms.iter().cloned());
+        if let Some(ref next_term_text) = self.next_term_text {
+            alternatives.push(format!("{}{}", self.term_text, next_term_text));
+        }
+        alternatives
+    }
+
+    /// Every way to cut `term_text` into two non-empty halves, e.g.
+    /// "speakers" -> [("s", "peakers"), ("sp", "eakers"), ...].
+    fn split_candidates(&self) -> Vec<(String, String)> {
+        let chars: Vec<char> = self.term_text.chars().collect();
+        (1..chars.len())
+            .map(|cut| (chars[..cut].iter().collect(), chars[cut..].iter().collect()))
+            .collect()
+    }
+}
+
+/// Builds a Levenshtein `DFA` for a single alternative word, so each
+/// alternative can be streamed against the term dictionary through the
+/// same `AutomatonWeight` machinery `FuzzyTermQuery` uses.
+#[derive(Debug, Clone)]
+struct WordAutomatonBuilder {
+    word: String,
+    distance: u8,
+    transposition_cost_one: bool,
+}
+
+impl AutomatonBuilder<DFA> for WordAutomatonBuilder {
+    fn build_automaton(&self) -> Box<DFA> {
+        let lev_automaton_builder =
+            LevenshteinAutomatonBuilder::new(self.distance, self.transposition_cost_one);
+        Box::new(lev_automaton_builder.build_dfa(&self.word))
+    }
+}
+
+impl Query for ExpandedTermQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        let word_weights = self
+            .word_alternatives()
+            .into_iter()
+            .map(|word| {
+                let builder = WordAutomatonBuilder {
+                    word,
+                    distance: self.distance,
+                    transposition_cost_one: self.transposition_cost_one,
+                };
+                AutomatonWeight::new(self.field, Arc::new(builder))
+            })
+            .collect();
+        Ok(Box::new(ExpandedTermWeight {
+            field: self.field,
+            word_weights,
+            split_candidates: self.split_candidates(),
+        }))
+    }
+}
+
+struct ExpandedTermWeight {
+    field: Field,
+    word_weights: Vec<AutomatonWeight<DFA>>,
+    split_candidates: Vec<(String, String)>,
+}
+
+impl ExpandedTermWeight {
+    /// Documents where `left` is immediately followed by `right`.
+    ///
+    /// A split candidate like "speaker" + "s" must only count when the
+    /// two fragments sit next to each other in the document — a
+    /// document that merely contains a standalone "s" elsewhere is not
+    /// a match. Without this check, splitting "speakers" would let any
+    /// document with a lone "s" score as if it contained "speakers".
+    fn adjacent_split_docs(&self, reader: &SegmentReader, left: &str, right: &str) -> Vec<DocId> {
+        let inverted_index = reader.inverted_index(self.field);
+        let term_dict = inverted_index.terms();
+        let left_info = match term_dict.get(left.as_bytes()) {
+            Some(term_info) => term_info,
+            None => return Vec::new(),
+        };
+        let right_info = match term_dict.get(right.as_bytes()) {
+            Some(term_info) => term_info,
+            None => return Vec::new(),
+        };
+
+        let mut left_postings = inverted_index
+            .read_postings_from_terminfo(&left_info, IndexRecordOption::WithFreqsAndPositions);
+        let mut right_postings = inverted_index
+            .read_postings_from_terminfo(&right_info, IndexRecordOption::WithFreqsAndPositions);
+
+        let mut matching_docs = Vec::new();
+        let mut left_positions = Vec::new();
+        let mut right_positions = Vec::new();
+        let mut right_has_doc = right_postings.advance();
+        while left_postings.advance() {
+            let doc = left_postings.doc();
+            while right_has_doc && right_postings.doc() < doc {
+                right_has_doc = right_postings.advance();
+            }
+            if !right_has_doc {
+                break;
+            }
+            if right_postings.doc() != doc {
+                continue;
+            }
+            left_postings.positions(&mut left_positions);
+            right_postings.positions(&mut right_positions);
+            let fragments_are_adjacent = left_positions
+                .iter()
+                .any(|&position| right_positions.contains(&(position + 1)));
+            if fragments_are_adjacent {
+                matching_docs.push(doc);
+            }
+        }
+        matching_docs
+    }
+}
+
+impl Weight for ExpandedTermWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+
+        for word_weight in &self.word_weights {
+            let mut scorer = word_weight.scorer(reader)?;
+            while scorer.advance() {
+                doc_bitset.insert(scorer.doc());
+            }
+        }
+
+        for &(ref left, ref right) in &self.split_candidates {
+            for doc in self.adjacent_split_docs(reader, left, right) {
+                doc_bitset.insert(doc);
+            }
+        }
+
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(Box::new(ConstScorer::new(doc_bitset)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ExpandedTermQuery;
+    use collector::TopCollector;
+    use schema::{SchemaBuilder, TEXT};
+    use Index;
+
+    /// "speakers" splits into candidates including ("speaker", "s"), so
+    /// a document with "speaker" immediately followed by "s" should
+    /// match. A document that merely contains a standalone "s" far from
+    /// "speaker" must not match, or the split candidate would wrongly
+    /// turn an unrelated "s" into a hit for "speakers".
+    #[test]
+    pub fn test_adjacent_split_suppresses_isolated_fragment() {
+        let mut schema_builder = SchemaBuilder::new();
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 10_000_000).unwrap();
+            index_writer.add_document(doc!(
+                body_field => "speaker s",
+            ));
+            index_writer.add_document(doc!(
+                body_field => "s is quiet today speaker",
+            ));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+
+        let mut collector = TopCollector::with_limit(2);
+        let query = ExpandedTermQuery::new(body_field, "speakers".to_string());
+        searcher.search(&query, &mut collector).unwrap();
+        let scored_docs = collector.score_docs();
+        assert_eq!(
+            scored_docs.len(),
+            1,
+            "only the document with adjacent \"speaker\"+\"s\" should match"
+        );
+    }
+}
@@ -1,16 +1,72 @@
 use common::BitSet;
 use core::SegmentReader;
 use fst::Automaton;
-use levenshtein_automata::{LevenshteinAutomatonBuilder, DFA};
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
 use query::automaton_builder::AutomatonBuilder;
-use query::BitSetDocSet;
-use query::ConstScorer;
+use query::{BitSetDocSet, ConstScorer};
 use query::{Query, Scorer, Weight};
 use schema::{Field, IndexRecordOption, Term};
+use std::sync::Arc;
 use termdict::{TermDictionary, TermStreamer};
+use DocId;
+use DocSet;
 use Result;
 use Searcher;
 
+/// The number of edits a query term is allowed, either a fixed budget
+/// or one derived from the length of the term itself.
+///
+/// Automatic mode mirrors the heuristic MeiliSearch's automaton
+/// producer uses: very short terms get no slack at all (a 1-edit
+/// budget on a 3-letter word matches almost anything), medium terms
+/// get one edit, and longer terms get two.
+#[derive(Debug, Clone)]
+enum FuzzyDistance {
+    Fixed(u8),
+    Auto(AutoDistanceThresholds),
+}
+
+impl FuzzyDistance {
+    fn resolve(&self, term_len: usize) -> u8 {
+        match *self {
+            FuzzyDistance::Fixed(distance) => distance,
+            FuzzyDistance::Auto(ref thresholds) => thresholds.distance_for(term_len),
+        }
+    }
+}
+
+/// Character-length thresholds used by `FuzzyTermQuery::new_auto` to
+/// pick an edit distance for a query term.
+#[derive(Debug, Clone)]
+pub struct AutoDistanceThresholds {
+    /// Terms with at most this many characters get zero edits.
+    pub max_len_for_0_edits: usize,
+    /// Terms with at most this many characters get one edit; longer
+    /// terms get two.
+    pub max_len_for_1_edit: usize,
+}
+
+impl AutoDistanceThresholds {
+    fn distance_for(&self, term_len: usize) -> u8 {
+        if term_len <= self.max_len_for_0_edits {
+            0
+        } else if term_len <= self.max_len_for_1_edit {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+impl Default for AutoDistanceThresholds {
+    fn default() -> AutoDistanceThresholds {
+        AutoDistanceThresholds {
+            max_len_for_0_edits: 4,
+            max_len_for_1_edit: 8,
+        }
+    }
+}
+
 /// A Fuzzy Query matches all of the documents
 /// containing a specific term that is within
 /// Levenshtein distance
@@ -19,7 +75,7 @@ pub struct FuzzyTermQuery {
     /// What term are we searching
     term: Term,
     /// How many changes are we going to allow
-    distance: u8,
+    distance: FuzzyDistance,
     /// Should a transposition cost 1 or 2?
     transposition_cost_one: bool,
     ///
@@ -31,7 +87,7 @@ impl FuzzyTermQuery {
     pub fn new(term: Term, distance: u8, transposition_cost_one: bool) -> FuzzyTermQuery {
         FuzzyTermQuery {
             term,
-            distance,
+            distance: FuzzyDistance::Fixed(distance),
             transposition_cost_one,
             prefix: false,
         }
@@ -40,17 +96,50 @@ impl FuzzyTermQuery {
     pub fn new_prefix(term: Term, distance: u8, transposition_cost_one: bool) -> FuzzyTermQuery {
         FuzzyTermQuery {
             term,
-            distance,
+            distance: FuzzyDistance::Fixed(distance),
             transposition_cost_one,
             prefix: true,
         }
     }
 
+    /// Creates a new Fuzzy Query whose edit distance is derived from
+    /// the length of `term`, using `AutoDistanceThresholds::default()`.
+    ///
+    /// This avoids the classic problem of a fixed 2-edit budget
+    /// turning a 3-letter query into noise, while still being lenient
+    /// on long words.
+    pub fn new_auto(term: Term, transposition_cost_one: bool) -> FuzzyTermQuery {
+        FuzzyTermQuery::new_auto_with_thresholds(
+            term,
+            transposition_cost_one,
+            AutoDistanceThresholds::default(),
+        )
+    }
+
+    /// Like `new_auto`, but with caller-supplied length thresholds.
+    pub fn new_auto_with_thresholds(
+        term: Term,
+        transposition_cost_one: bool,
+        thresholds: AutoDistanceThresholds,
+    ) -> FuzzyTermQuery {
+        FuzzyTermQuery {
+            term,
+            distance: FuzzyDistance::Auto(thresholds),
+            transposition_cost_one,
+            prefix: false,
+        }
+    }
+
+    fn distance_for_term(&self) -> u8 {
+        self.distance.resolve(self.term.text().chars().count())
+    }
+
     pub fn specialized_weight(&self) -> AutomatonWeight<DFA> {
         AutomatonWeight {
             field: self.term.field(),
             // TODO: is there a better way to do this?
-            builder: Box::new(self.clone()),
+            builder: Arc::new(self.clone()),
+            distance: Some(Box::new(self.clone())),
         }
     }
 }
@@ -64,7 +153,7 @@ impl Query for FuzzyTermQuery {
 impl AutomatonBuilder<DFA> for FuzzyTermQuery {
     fn build_automaton(&self) -> Box<DFA> {
         let lev_automaton_builder =
-            LevenshteinAutomatonBuilder::new(self.distance, self.transposition_cost_one);
+            LevenshteinAutomatonBuilder::new(self.distance_for_term(), self.transposition_cost_one);
 
         let automaton = if self.prefix {
             lev_automaton_builder.build_prefix_dfa(self.term.text())
@@ -76,18 +165,118 @@ impl AutomatonBuilder<DFA> for FuzzyTermQuery {
     }
 }
 
+/// Something that can tell how well a matched term fits the query that
+/// produced it, so `AutomatonWeight` can score close matches higher
+/// than distant ones instead of treating every match as equal.
+///
+/// `term_scorer` is called once per segment scorer, not once per
+/// matched term: `LevenshteinAutomatonBuilder::new` precomputes a
+/// parametric DFA and is the expensive step, so the resulting `DFA`s
+/// are built once here and then only `eval`-ed per term.
+trait AutomatonDistance {
+    fn term_scorer(&self) -> Box<TermScorer>;
+}
+
+/// Scores a single matched term against the precomputed automata a
+/// `term_scorer()` call built.
+trait TermScorer {
+    fn score_for_term(&self, term_bytes: &[u8]) -> f32;
+}
+
+/// An exact match outranks a prefix-exact match (the query is a prefix
+/// of the term, but the term has extra trailing characters), which in
+/// turn outranks a distance-1 fuzzy match, then distance-2.
+const EXACT_SCORE: f32 = 1.0;
+const PREFIX_EXACT_SCORE: f32 = 0.75;
+
+/// Precomputed DFAs used to score every term a `FuzzyTermQuery` hits
+/// in a given segment.
+struct FuzzyTermScorer {
+    fuzzy_dfa: DFA,
+    /// A plain 0-edit DFA for the query term, built the same way
+    /// MeiliSearch's `build_exact_dfa` does. Kept separate from
+    /// `fuzzy_dfa` so we can tell an exact match apart from a
+    /// merely-close one even when `prefix` is set, where a 0-edit
+    /// result from `fuzzy_dfa` only means the term *starts with* the
+    /// query, not that it equals it.
+    exact_dfa: DFA,
+    prefix: bool,
+}
+
+impl TermScorer for FuzzyTermScorer {
+    fn score_for_term(&self, term_bytes: &[u8]) -> f32 {
+        let edit_distance = match self.fuzzy_dfa.eval(term_bytes) {
+            Distance::Exact(d) | Distance::AtLeast(d) => d,
+        };
+        let is_exact = match self.exact_dfa.eval(term_bytes) {
+            Distance::Exact(0) => true,
+            _ => false,
+        };
+
+        if is_exact {
+            EXACT_SCORE
+        } else if self.prefix && edit_distance == 0 {
+            PREFIX_EXACT_SCORE
+        } else {
+            distance_to_score(edit_distance)
+        }
+    }
+}
+
+impl AutomatonDistance for FuzzyTermQuery {
+    fn term_scorer(&self) -> Box<TermScorer> {
+        let lev_automaton_builder =
+            LevenshteinAutomatonBuilder::new(self.distance_for_term(), self.transposition_cost_one);
+        let fuzzy_dfa = if self.prefix {
+            lev_automaton_builder.build_prefix_dfa(self.term.text())
+        } else {
+            lev_automaton_builder.build_dfa(self.term.text())
+        };
+        let exact_dfa = LevenshteinAutomatonBuilder::new(0, self.transposition_cost_one)
+            .build_dfa(self.term.text());
+        Box::new(FuzzyTermScorer {
+            fuzzy_dfa,
+            exact_dfa,
+            prefix: self.prefix,
+        })
+    }
+}
+
+/// Maps an edit distance to a score, so that a distance-1 match
+/// outranks a distance-2 match. Exact and prefix-exact matches are
+/// scored separately, above anything this function returns.
+fn distance_to_score(distance: u8) -> f32 {
+    1.0 / (2.0 + distance as f32)
+}
+
 pub struct AutomatonWeight<A>
 where
     A: Automaton,
 {
     field: Field,
-    builder: Box<AutomatonBuilder<A>>,
+    builder: Arc<AutomatonBuilder<A>>,
+    /// When set, matched terms are scored by how close they are to the
+    /// query rather than all receiving a constant score of 1.0.
+    distance: Option<Box<AutomatonDistance>>,
 }
 
 impl<A> AutomatonWeight<A>
 where
     A: Automaton,
 {
+    /// Creates a weight that matches every term `builder`'s automaton
+    /// accepts, with no distance-aware scoring: every match scores a
+    /// constant 1.0. This is the entry point `AutomatonQuery` and
+    /// `RegexQuery` use; `FuzzyTermQuery` goes through
+    /// `specialized_weight` instead to also get distance scoring.
+    pub fn new(field: Field, builder: Arc<AutomatonBuilder<A>>) -> AutomatonWeight<A> {
+        AutomatonWeight {
+            field,
+            builder,
+            distance: None,
+        }
+    }
+
     fn automaton_stream<'a>(&self, term_dict: &'a TermDictionary) -> TermStreamer<'a, A> {
         let automaton = self.builder.build_automaton();
 
@@ -105,27 +294,94 @@ where
         let max_doc = reader.max_doc();
         let mut doc_bitset = BitSet::with_max_value(max_doc);
 
+        // Built once per segment, not once per matched term: building
+        // the underlying DFAs is the expensive part of scoring.
+        let term_scorer = self
+            .distance
+            .as_ref()
+            .map(|distance| distance.term_scorer());
+
+        // Without a distance scorer every match scores a constant 1.0,
+        // so there is nothing worth tracking per doc: skip the
+        // per-segment `doc_scores` vec and hand back a `ConstScorer`,
+        // same as a plain term query would.
+        let mut doc_scores = if term_scorer.is_some() {
+            Some(vec![0f32; max_doc as usize])
+        } else {
+            None
+        };
+
         let inverted_index = reader.inverted_index(self.field);
         let term_dict = inverted_index.terms();
         let mut term_stream = self.automaton_stream(term_dict);
         while term_stream.advance() {
+            let score = term_scorer
+                .as_ref()
+                .map(|term_scorer| term_scorer.score_for_term(term_stream.key()));
             let term_info = term_stream.value();
             let mut block_segment_postings = inverted_index
                 .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
             while block_segment_postings.advance() {
                 for &doc in block_segment_postings.docs() {
                     doc_bitset.insert(doc);
+                    if let (Some(score), Some(doc_scores)) = (score, doc_scores.as_mut()) {
+                        let best_score = &mut doc_scores[doc as usize];
+                        if score > *best_score {
+                            *best_score = score;
+                        }
+                    }
                 }
             }
         }
         let doc_bitset = BitSetDocSet::from(doc_bitset);
-        Ok(Box::new(ConstScorer::new(doc_bitset)))
+        match doc_scores {
+            Some(doc_scores) => Ok(Box::new(AutomatonScorer::new(doc_bitset, doc_scores))),
+            None => Ok(Box::new(ConstScorer::new(doc_bitset))),
+        }
+    }
+}
+
+/// A `Scorer` over the documents matched by an `AutomatonWeight`, whose
+/// score for each document reflects the best (e.g. closest edit
+/// distance, or exact) term that matched it.
+struct AutomatonScorer {
+    doc_bitset: BitSetDocSet,
+    doc_scores: Vec<f32>,
+}
+
+impl AutomatonScorer {
+    fn new(doc_bitset: BitSetDocSet, doc_scores: Vec<f32>) -> AutomatonScorer {
+        AutomatonScorer {
+            doc_bitset,
+            doc_scores,
+        }
+    }
+}
+
+impl DocSet for AutomatonScorer {
+    fn advance(&mut self) -> bool {
+        self.doc_bitset.advance()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc_bitset.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.doc_bitset.size_hint()
+    }
+}
+
+impl Scorer for AutomatonScorer {
+    fn score(&mut self) -> f32 {
+        let doc = self.doc_bitset.doc();
+        self.doc_scores[doc as usize]
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::FuzzyTermQuery;
+    use super::{AutoDistanceThresholds, AutomatonDistance, FuzzyTermQuery, TermScorer};
     use collector::TopCollector;
     use schema::{SchemaBuilder, TEXT};
     use tests::assert_nearly_equals;
@@ -159,7 +415,41 @@ mod test {
             let scored_docs = collector.score_docs();
             assert_eq!(scored_docs.len(), 1, "Expected only 1 document");
             let (score, _) = scored_docs[0];
-            assert_nearly_equals(1f32, score);
+            // "japon" is a single edit away from the indexed "japan", so
+            // it should be scored lower than an exact match would be.
+            assert_nearly_equals(1.0 / 3.0, score);
         }
     }
+
+    #[test]
+    pub fn test_auto_distance_thresholds() {
+        let thresholds = AutoDistanceThresholds::default();
+        assert_eq!(thresholds.distance_for(4), 0);
+        assert_eq!(thresholds.distance_for(5), 1);
+        assert_eq!(thresholds.distance_for(9), 2);
+    }
+
+    /// Exact > prefix-exact > distance-1 > distance-2, per request
+    /// chunk0-6.
+    #[test]
+    pub fn test_score_for_term_orders_exact_above_fuzzy() {
+        let mut schema_builder = SchemaBuilder::new();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let _schema = schema_builder.build();
+        let term = Term::from_field_text(text_field, "japan");
+
+        let fuzzy_query = FuzzyTermQuery::new(term.clone(), 2, true);
+        let term_scorer = fuzzy_query.term_scorer();
+        let exact_score = term_scorer.score_for_term(b"japan");
+        let distance_1_score = term_scorer.score_for_term(b"japon");
+        let distance_2_score = term_scorer.score_for_term(b"jacon");
+        assert!(exact_score > distance_1_score);
+        assert!(distance_1_score > distance_2_score);
+
+        let prefix_query = FuzzyTermQuery::new_prefix(term, 2, true);
+        let prefix_term_scorer = prefix_query.term_scorer();
+        let prefix_exact_score = prefix_term_scorer.score_for_term(b"japanese");
+        assert!(exact_score > prefix_exact_score);
+        assert!(prefix_exact_score > distance_1_score);
+    }
 }
@@ -0,0 +1,9 @@
+mod automaton_query;
+mod expanded_term_query;
+mod fuzzy_query;
+mod regex_query;
+
+pub use self::automaton_query::AutomatonQuery;
+pub use self::expanded_term_query::ExpandedTermQuery;
+pub use self::fuzzy_query::{AutoDistanceThresholds, AutomatonWeight, FuzzyTermQuery};
+pub use self::regex_query::RegexQuery;
@@ -0,0 +1,47 @@
+use fst::Regex;
+use query::automaton_builder::AutomatonBuilder;
+use query::{AutomatonWeight, Query, Weight};
+use schema::Field;
+use std::sync::Arc;
+use Error;
+use Result;
+use Searcher;
+
+/// A Query that matches every term accepted by a regular expression,
+/// streamed against the field's term dictionary through the same
+/// `AutomatonWeight` path `FuzzyTermQuery` uses.
+#[derive(Debug, Clone)]
+pub struct RegexQuery {
+    field: Field,
+    pattern: String,
+}
+
+impl RegexQuery {
+    /// Creates a new `RegexQuery` matching `pattern` against `field`.
+    ///
+    /// `pattern` is compiled eagerly, so an invalid regular expression
+    /// is reported here rather than panicking later at search time.
+    pub fn new(field: Field, pattern: String) -> Result<RegexQuery> {
+        Regex::new(&pattern).map_err(|err| {
+            Error::InvalidArgument(format!("invalid regex {:?}: {}", pattern, err))
+        })?;
+        Ok(RegexQuery { field, pattern })
+    }
+}
+
+impl AutomatonBuilder<Regex> for RegexQuery {
+    fn build_automaton(&self) -> Box<Regex> {
+        let regex = Regex::new(&self.pattern)
+            .expect("RegexQuery::new already validated that this pattern compiles");
+        Box::new(regex)
+    }
+}
+
+impl Query for RegexQuery {
+    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(Box::new(AutomatonWeight::new(
+            self.field,
+            Arc::new(self.clone()),
+        )))
+    }
+}